@@ -0,0 +1,580 @@
+//! `tailwag generate resource` - generates a new resource and registers it with
+//! the service.
+//!
+//! ```text
+//! tailwag generate resource <Name> [field:type ...] [--policy public|authenticated] [--read-policy public|authenticated] [--main <path>] [--dry-run]
+//! ```
+//!
+//! Types can be given with shorthands (`string`, `int`, `bool`, `uuid`,
+//! `datetime`) or as one of the Rust types they map to. A trailing `?` makes
+//! the field optional, e.g. `description:string?`. An `id: uuid::Uuid` field is
+//! always added.
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use super::{
+    Args,
+    CliResult,
+};
+
+/// (shorthands, Rust type) for the field types the ORM supports.
+const FIELD_TYPES: &[(&[&str], &str)] = &[
+    (&["string", "str", "text"], "String"),
+    (&["int", "integer", "bigint", "long"], "i64"),
+    (&["bool", "boolean"], "bool"),
+    (&["uuid"], "uuid::Uuid"),
+    (&["datetime", "timestamp"], "chrono::NaiveDateTime"),
+];
+
+/// The start of the `WebService` builder chain, which resources are registered
+/// on.
+const BUILDER_ANCHOR: &str = "WebService::builder(";
+
+struct Field {
+    name:      String,
+    rust_type: String,
+    optional:  bool,
+}
+
+pub fn run(args: Args) -> CliResult {
+    match args.positional.first().map(String::as_str) {
+        Some("resource") => generate_resource(&args),
+        _ => Err("Usage: tailwag generate resource <Name> [field:type ...] [--policy \
+                  public|authenticated] [--read-policy public|authenticated] [--main <path>] \
+                  [--dry-run]"
+            .into()),
+    }
+}
+
+fn generate_resource(args: &Args) -> CliResult {
+    let Some(name) = args.positional.get(1) else {
+        Err("Missing resource name, e.g. `tailwag generate resource Product name:string`")?
+    };
+    if !name.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        || !name.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        Err(format!("Invalid resource name `{name}`. Resource names must be PascalCase."))?;
+    }
+    let fields = args.positional[2..]
+        .iter()
+        .map(|f| parse_field(f))
+        .collect::<CliResult<Vec<_>>>()?;
+
+    let policy = args.option("policy").map(parse_policy).transpose()?;
+    let read_policy = args.option("read-policy").map(parse_policy).transpose()?;
+
+    let main_path = PathBuf::from(args.option("main").unwrap_or("src/main.rs"));
+    let module = to_snake_case(name);
+    let module_path = main_path.with_file_name(format!("{module}.rs"));
+    if module_path.exists() {
+        Err(format!("{} already exists.", module_path.display()))?;
+    }
+
+    let resource_source = resource_source(name, &fields, policy, read_policy);
+    let main_source = std::fs::read_to_string(&main_path)?;
+    let main_edit = register_resource(&main_source, &module, name)?;
+
+    if args.switch("dry-run") {
+        print_new_file(&module_path, &resource_source);
+        print!("{}", main_edit.diff(&main_path));
+        return Ok(());
+    }
+
+    std::fs::write(&module_path, resource_source)?;
+    std::fs::write(&main_path, main_edit.to_string())?;
+    println!("Created {}", module_path.display());
+    println!("Registered {name} in {}", main_path.display());
+    Ok(())
+}
+
+fn parse_field(arg: &str) -> CliResult<Field> {
+    let Some((name, field_type)) = arg.split_once(':') else {
+        Err(format!("Invalid field `{arg}`. Fields must be given as `name:type`."))?
+    };
+    let valid_name = name.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid_name {
+        Err(format!("Invalid field name `{name}`. Field names must be snake_case."))?;
+    }
+    if name == "id" {
+        Err("The `id` field is added automatically.")?;
+    }
+    let (field_type, optional) = match field_type.strip_suffix('?') {
+        Some(field_type) => (field_type, true),
+        None => (field_type, false),
+    };
+    Ok(Field {
+        name: name.to_string(),
+        rust_type: rust_type(field_type)?.to_string(),
+        optional,
+    })
+}
+
+/// Maps a shorthand or Rust type name to the Rust type, rejecting anything the
+/// ORM can't store.
+fn rust_type(field_type: &str) -> CliResult<&'static str> {
+    let found = FIELD_TYPES.iter().find(|(shorthands, rust_type)| {
+        shorthands.contains(&field_type) || *rust_type == field_type
+    });
+    match found {
+        Some((_, rust_type)) => Ok(rust_type),
+        None => {
+            let supported = FIELD_TYPES
+                .iter()
+                .map(|(shorthands, rust_type)| format!("{} ({rust_type})", shorthands[0]))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(format!("Unsupported type `{field_type}`. Supported types: {supported}.").into())
+        },
+    }
+}
+
+fn parse_policy(policy: &str) -> CliResult<&'static str> {
+    match policy {
+        "public" => Ok("RoutePolicy::Public"),
+        "authenticated" | "auth" => Ok("RoutePolicy::RequireAuthentication"),
+        other => {
+            Err(format!("Unknown policy `{other}`. Expected `public` or `authenticated`.").into())
+        },
+    }
+}
+
+fn resource_source(
+    name: &str,
+    fields: &[Field],
+    policy: Option<&str>,
+    read_policy: Option<&str>,
+) -> String {
+    let mut source = String::from("use tailwag::prelude::*;\n\nderive_magic! {\n");
+    let read_policy = read_policy.or(policy);
+    for (route, route_policy) in [
+        ("get", read_policy),
+        ("list", read_policy),
+        ("post", policy),
+        ("patch", policy),
+        ("delete", policy),
+    ] {
+        if let Some(route_policy) = route_policy {
+            source.push_str(&format!("    #[{route}_policy({route_policy})]\n"));
+        }
+    }
+
+    source.push_str(&format!("    pub struct {name} {{\n        id: uuid::Uuid,\n"));
+    let display_field = fields.iter().position(|f| f.rust_type == "String" && !f.optional);
+    for (i, field) in fields.iter().enumerate() {
+        if display_field == Some(i) {
+            source.push_str("        #[display]\n");
+        }
+        let rust_type = match field.optional {
+            // Optional fields can't be filtered on yet.
+            true => {
+                source.push_str("        #[no_filter]\n");
+                format!("Option<{}>", field.rust_type)
+            },
+            false => field.rust_type.clone(),
+        };
+        source.push_str(&format!("        {}: {rust_type},\n", field.name));
+    }
+    source.push_str("    }\n}\n");
+    source
+}
+
+/// Adds `mod <module>; use <module>::<Name>;` to the service's main file, and
+/// registers the resource on the `WebService` builder, after its last
+/// `.with_resource::<T>()`.
+fn register_resource(
+    source: &str,
+    module: &str,
+    name: &str,
+) -> CliResult<SourceEdit> {
+    let mut edit = SourceEdit::new(source);
+    let lines: Vec<&str> = source.lines().collect();
+    let registration = format!(".with_resource::<{name}>()");
+    if source.contains(&registration) {
+        Err(format!("{name} is already registered."))?;
+    }
+
+    let is_mod = |line: &str| {
+        let line = line.strip_prefix("pub ").unwrap_or(line);
+        line.starts_with("mod ") && line.ends_with(';')
+    };
+    let modules: Vec<String> = lines
+        .iter()
+        .filter(|line| is_mod(line))
+        .map(|line| {
+            line.trim_start_matches("pub ")
+                .trim_start_matches("mod ")
+                .trim_end_matches(';')
+                .to_string()
+        })
+        .collect();
+    let last_mod = lines.iter().rposition(|line| is_mod(line));
+    let last_local_use = lines
+        .iter()
+        .rposition(|line| modules.iter().any(|m| line.starts_with(&format!("use {m}::"))));
+    let last_use_end = last_top_level_use_end(&lines);
+
+    let mod_line = format!("mod {module};");
+    let use_line = format!("use {module}::{name};");
+    match (last_mod, last_local_use, last_use_end) {
+        (Some(last_mod), Some(last_local_use), _) => {
+            edit.insert_after(last_mod, vec![mod_line]);
+            edit.insert_after(last_local_use, vec![use_line]);
+        },
+        (Some(last_mod), None, _) => edit.insert_after(last_mod, vec![mod_line, use_line]),
+        (None, _, Some(last_use_end)) => {
+            edit.insert_after(last_use_end, vec![String::new(), mod_line, use_line])
+        },
+        (None, _, None) => edit.insert_before(0, vec![mod_line, use_line, String::new()]),
+    }
+
+    let Some(start) = lines.iter().position(|line| line.contains(BUILDER_ANCHOR)) else {
+        Err(format!("Could not find `{BUILDER_ANCHOR}..)` to register the resource with."))?
+    };
+    // The chain is every following line that continues the method calls.
+    let chain_len = lines[start + 1..]
+        .iter()
+        .take_while(|line| line.trim_start().starts_with('.'))
+        .count();
+    let last_registration = lines[start + 1..=start + chain_len]
+        .iter()
+        .rposition(|line| line.trim_start().starts_with(".with_resource::<"))
+        .map(|i| start + 1 + i);
+    let indent = match last_registration {
+        Some(i) => indent_of(lines[i]),
+        None => indent_of(lines[start]) + 4,
+    };
+    edit.insert_after(
+        last_registration.unwrap_or(start),
+        vec![format!("{}{registration}", " ".repeat(indent))],
+    );
+    Ok(edit)
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Finds the last line of the last top-level `use` statement,
+/// accounting for statements that span multiple lines.
+fn last_top_level_use_end(lines: &[&str]) -> Option<usize> {
+    let mut last_end = None;
+    let mut in_use = false;
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with("use ") || line.starts_with("pub use ") {
+            in_use = true;
+        }
+        if in_use && line.trim_end().ends_with(';') {
+            in_use = false;
+            last_end = Some(i);
+        }
+    }
+    last_end
+}
+
+/// `ShopOrder` -> `shop_order`. Runs of capitals are treated as a single word,
+/// so `HTTPServer` -> `http_server`.
+fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_ascii_lowercase());
+            if !prev.is_ascii_uppercase() || next_is_lower {
+                snake.push('_');
+            }
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+fn print_new_file(
+    path: &Path,
+    contents: &str,
+) {
+    println!("--- /dev/null");
+    println!("+++ {}", path.display());
+    println!("@@ -0,0 +1,{} @@", contents.lines().count());
+    for line in contents.lines() {
+        println!("+{line}");
+    }
+}
+
+/// A set of line insertions into an existing file, which can be rendered
+/// either as the new contents or as a unified diff.
+struct SourceEdit {
+    /// Each original line, with the lines to insert before and after it.
+    lines: Vec<(Vec<String>, String, Vec<String>)>,
+}
+
+impl SourceEdit {
+    fn new(source: &str) -> Self {
+        Self {
+            lines: source.lines().map(|line| (Vec::new(), line.to_string(), Vec::new())).collect(),
+        }
+    }
+
+    fn insert_before(
+        &mut self,
+        index: usize,
+        new_lines: Vec<String>,
+    ) {
+        match self.lines.get_mut(index) {
+            Some((before, _, _)) => before.extend(new_lines),
+            None => self.lines.push((new_lines, String::new(), Vec::new())),
+        }
+    }
+
+    fn insert_after(
+        &mut self,
+        index: usize,
+        new_lines: Vec<String>,
+    ) {
+        self.lines[index].2.extend(new_lines);
+    }
+
+    /// Every line of the edited file, flagged `true` if it was inserted.
+    fn edited_lines(&self) -> Vec<(bool, &str)> {
+        let mut edited = Vec::new();
+        for (before, line, after) in &self.lines {
+            edited.extend(before.iter().map(|l| (true, l.as_str())));
+            edited.push((false, line.as_str()));
+            edited.extend(after.iter().map(|l| (true, l.as_str())));
+        }
+        edited
+    }
+
+    /// The edit as a unified diff against `path`.
+    fn diff(
+        &self,
+        path: &Path,
+    ) -> String {
+        const CONTEXT: usize = 3;
+        let edited = self.edited_lines();
+        let mut diff = format!("--- {0}\n+++ {0}\n", path.display());
+
+        let mut i = 0;
+        while i < edited.len() {
+            if !edited[i].0 {
+                i += 1;
+                continue;
+            }
+            // Grow the hunk until there are more than 2 * CONTEXT unchanged lines in a row.
+            let start = i.saturating_sub(CONTEXT);
+            let mut end = i;
+            while end < edited.len() {
+                let next_insert = edited[end..].iter().position(|(inserted, _)| *inserted);
+                match next_insert {
+                    Some(offset) if offset <= 2 * CONTEXT => end += offset + 1,
+                    _ => break,
+                }
+            }
+            let end = (end + CONTEXT).min(edited.len());
+
+            let hunk = &edited[start..end];
+            let old_start = edited[..start].iter().filter(|(inserted, _)| !inserted).count() + 1;
+            let old_len = hunk.iter().filter(|(inserted, _)| !inserted).count();
+            let new_start = start + 1;
+            diff.push_str(&format!("@@ -{old_start},{old_len} +{new_start},{} @@\n", hunk.len()));
+            for (inserted, line) in hunk {
+                let prefix = if *inserted {
+                    '+'
+                } else {
+                    ' '
+                };
+                diff.push_str(&format!("{prefix}{line}\n"));
+            }
+            i = end;
+        }
+        diff
+    }
+}
+
+impl std::fmt::Display for SourceEdit {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        for (_, line) in self.edited_lines() {
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL_MAIN: &str = include_str!("templates/minimal/main.rs");
+    const AUTH_MAIN: &str = include_str!("templates/auth/main.rs");
+    const SHOP_MAIN: &str = include_str!("templates/shop/main.rs");
+
+    fn register(
+        source: &str,
+        name: &str,
+    ) -> String {
+        register_resource(source, &to_snake_case(name), name).unwrap().to_string()
+    }
+
+    /// The `WebService` builder chain, one call per line.
+    fn builder_chain(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .skip_while(|line| !line.contains(BUILDER_ANCHOR))
+            .skip(1)
+            .take_while(|line| line.trim_start().starts_with('.'))
+            .collect()
+    }
+
+    #[test]
+    fn registers_with_minimal_template() {
+        let edited = register(MINIMAL_MAIN, "Tag");
+        assert!(edited.contains("mod todo;\nmod tag;\nuse todo::Todo;\nuse tag::Tag;\n"));
+        assert_eq!(
+            builder_chain(&edited)[..3],
+            [
+                "        .with_resource::<Todo>()",
+                "        .with_resource::<Tag>()",
+                "        .build_service()",
+            ]
+        );
+        assert_eq!(edited.lines().count(), MINIMAL_MAIN.lines().count() + 3);
+    }
+
+    #[test]
+    fn registers_with_auth_template() {
+        let edited = register(AUTH_MAIN, "Notebook");
+        assert!(
+            edited.contains("mod note;\nmod notebook;\nuse note::Note;\nuse notebook::Notebook;\n")
+        );
+        assert_eq!(
+            builder_chain(&edited)[4..6],
+            ["        .with_resource::<Note>()", "        .with_resource::<Notebook>()",]
+        );
+    }
+
+    #[test]
+    fn registers_with_shop_template() {
+        let edited = register(SHOP_MAIN, "ProductReview");
+        assert!(edited.contains(
+            "mod order;\nmod product;\nmod product_review;\nuse order::ShopOrder;\nuse \
+             product::Product;\nuse product_review::ProductReview;\n"
+        ));
+        assert_eq!(
+            builder_chain(&edited)[5..8],
+            [
+                "        .with_resource::<ShopOrder>()",
+                "        .with_resource::<ProductReview>()",
+                "        .build_service()",
+            ]
+        );
+    }
+
+    #[test]
+    fn registers_on_builders_without_resources() {
+        let source = "use tailwag::prelude::*;\n\nfn main() {\n    \
+                      WebService::builder(\"Service\")\n        .build_service();\n}\n";
+        let edited = register(source, "Todo");
+        assert!(edited.contains("use tailwag::prelude::*;\n\nmod todo;\nuse todo::Todo;\n\n"));
+        assert!(
+            edited.contains("WebService::builder(\"Service\")\n        .with_resource::<Todo>()\n")
+        );
+    }
+
+    #[test]
+    fn rejects_unregisterable_sources() {
+        let Err(err) = register_resource(MINIMAL_MAIN, "todo", "Todo") else {
+            panic!("Todo should already be registered");
+        };
+        assert_eq!(err.to_string(), "Todo is already registered.");
+
+        let without_builder = MINIMAL_MAIN.replace(BUILDER_ANCHOR, "Service::new(");
+        assert!(register_resource(&without_builder, "tag", "Tag").is_err());
+    }
+
+    #[test]
+    fn diffs_in_hunks() {
+        let edit = register_resource(SHOP_MAIN, "tag", "Tag").unwrap();
+        let diff = edit.diff(Path::new("src/main.rs"));
+        let hunks: Vec<&str> = diff.lines().filter(|line| line.starts_with("@@")).collect();
+        assert_eq!(hunks, ["@@ -8,8 +8,10 @@", "@@ -20,6 +22,7 @@"]);
+        assert!(diff.starts_with(
+            "--- src/main.rs\n+++ src/main.rs\n@@ -8,8 +8,10 @@\n \n mod order;\n mod \
+             product;\n+mod tag;\n use order::ShopOrder;\n use product::Product;\n+use tag::Tag;\n"
+        ));
+        let added: Vec<&str> = diff
+            .lines()
+            .filter(|line| line.starts_with('+') && !line.starts_with("+++"))
+            .collect();
+        assert_eq!(added, ["+mod tag;", "+use tag::Tag;", "+        .with_resource::<Tag>()"]);
+    }
+
+    #[test]
+    fn generates_derive_magic_resources() {
+        let fields = ["title:string", "notes:text?", "count:i64"]
+            .iter()
+            .map(|f| parse_field(f))
+            .collect::<CliResult<Vec<_>>>()
+            .unwrap();
+        let source = resource_source(
+            "Review",
+            &fields,
+            Some("RoutePolicy::RequireAuthentication"),
+            Some("RoutePolicy::Public"),
+        );
+        assert_eq!(
+            source,
+            "use tailwag::prelude::*;
+
+derive_magic! {
+    #[get_policy(RoutePolicy::Public)]
+    #[list_policy(RoutePolicy::Public)]
+    #[post_policy(RoutePolicy::RequireAuthentication)]
+    #[patch_policy(RoutePolicy::RequireAuthentication)]
+    #[delete_policy(RoutePolicy::RequireAuthentication)]
+    pub struct Review {
+        id: uuid::Uuid,
+        #[display]
+        title: String,
+        #[no_filter]
+        notes: Option<String>,
+        count: i64,
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn converts_names_to_snake_case() {
+        assert_eq!(to_snake_case("Todo"), "todo");
+        assert_eq!(to_snake_case("ShopOrder"), "shop_order");
+        assert_eq!(to_snake_case("HTTPServer"), "http_server");
+        assert_eq!(to_snake_case("ApiURL"), "api_url");
+        assert_eq!(to_snake_case("Order2Item"), "order2_item");
+    }
+
+    #[test]
+    fn maps_supported_types() {
+        assert_eq!(rust_type("int").unwrap(), "i64");
+        assert_eq!(rust_type("bigint").unwrap(), "i64");
+        assert_eq!(rust_type("datetime").unwrap(), "chrono::NaiveDateTime");
+        assert_eq!(rust_type("uuid::Uuid").unwrap(), "uuid::Uuid");
+        assert_eq!(rust_type("String").unwrap(), "String");
+    }
+
+    #[test]
+    fn rejects_unsupported_types() {
+        for field_type in ["float", "f32", "i32", "Vec<String>", "Uuid", ""] {
+            let err = rust_type(field_type).unwrap_err();
+            assert!(err.to_string().starts_with(&format!("Unsupported type `{field_type}`")));
+        }
+        assert!(parse_field("price:float").is_err());
+        assert!(parse_field("price:float?").is_err());
+    }
+}
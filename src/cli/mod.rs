@@ -0,0 +1,108 @@
+pub mod generate;
+pub mod migrate;
+pub mod new;
+
+use std::collections::HashMap;
+
+pub type CliResult<T = ()> = Result<T, Box<dyn std::error::Error>>;
+
+/// A minimal argument parser for the `tailwag` subcommands.
+///
+/// Supports positional arguments, `--flag value` / `--flag=value` options, and
+/// boolean switches (which must be listed up front so they don't swallow the
+/// next argument). Flags that aren't listed as a switch or option are
+/// rejected, so that a typo doesn't silently fall back to the default.
+#[derive(Debug, Default)]
+pub struct Args {
+    pub positional: Vec<String>,
+    options:        HashMap<String, String>,
+    switches:       Vec<String>,
+}
+
+impl Args {
+    pub fn parse(
+        args: impl Iterator<Item = String>,
+        switches: &[&str],
+        options: &[&str],
+    ) -> CliResult<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                parsed.positional.push(arg);
+                continue;
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (flag, None),
+            };
+            if switches.contains(&name) && value.is_none() {
+                parsed.switches.push(name.to_string());
+            } else if options.contains(&name) {
+                let value = match value {
+                    Some(value) => value,
+                    None => args.next().ok_or(format!("Missing value for --{name}"))?,
+                };
+                parsed.options.insert(name.to_string(), value);
+            } else {
+                Err(format!("Unknown option --{name}"))?;
+            }
+        }
+        Ok(parsed)
+    }
+
+    pub fn switch(
+        &self,
+        name: &str,
+    ) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+
+    pub fn option(
+        &self,
+        name: &str,
+    ) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> CliResult<Args> {
+        Args::parse(
+            args.iter().map(|arg| arg.to_string()),
+            &["dry-run", "allow-drop"],
+            &["schema", "name"],
+        )
+    }
+
+    #[test]
+    fn parses_positionals_options_and_switches() {
+        let args =
+            parse(&["up", "--dry-run", "extra", "--schema", "schema.json", "--name=init"]).unwrap();
+        assert_eq!(args.positional, ["up", "extra"]);
+        assert!(args.switch("dry-run"));
+        assert!(!args.switch("allow-drop"));
+        assert_eq!(args.option("schema"), Some("schema.json"));
+        assert_eq!(args.option("name"), Some("init"));
+        assert_eq!(args.option("dry-run"), None);
+    }
+
+    #[test]
+    fn rejects_options_without_values() {
+        let err = parse(&["--name"]).unwrap_err();
+        assert_eq!(err.to_string(), "Missing value for --name");
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        let err = parse(&["up", "--nmae", "init"]).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown option --nmae");
+        let err = parse(&["--nmae=init"]).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown option --nmae");
+        let err = parse(&["--dry-run=true"]).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown option --dry-run");
+    }
+}
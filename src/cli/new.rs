@@ -0,0 +1,197 @@
+//! `tailwag new` - scaffolds a new Cargo project from one of the built-in
+//! templates.
+//!
+//! ```text
+//! tailwag new <name> [--template minimal|auth|shop]
+//! ```
+use std::path::Path;
+
+use super::{
+    Args,
+    CliResult,
+};
+
+/// A file to write into the new project, relative to the project root.
+type TemplateFile = (&'static str, &'static str);
+
+const COMMON_FILES: &[TemplateFile] = &[
+    ("Cargo.toml", include_str!("templates/common/Cargo.toml")),
+    (".env", include_str!("templates/common/env")),
+    (".gitignore", include_str!("templates/common/gitignore")),
+    ("docker-compose.yml", include_str!("templates/common/docker-compose.yml")),
+    ("README.md", include_str!("templates/common/README.md")),
+];
+
+/// (template name, description, files)
+const TEMPLATES: &[(&str, &str, &[TemplateFile])] = &[
+    (
+        "minimal",
+        "A single `Todo` resource with full CRUD routes.",
+        &[
+            ("src/main.rs", include_str!("templates/minimal/main.rs")),
+            ("src/todo.rs", include_str!("templates/minimal/todo.rs")),
+            ("tests__hurl/todo.hurl", include_str!("templates/minimal/test.hurl")),
+        ],
+    ),
+    (
+        "auth",
+        "Login / registration, with a `Note` resource that requires authentication.",
+        &[
+            ("src/main.rs", include_str!("templates/auth/main.rs")),
+            ("src/note.rs", include_str!("templates/auth/note.rs")),
+            ("tests__hurl/login_register_work.hurl", include_str!("templates/auth/test.hurl")),
+        ],
+    ),
+    (
+        "shop",
+        "Public `Product` catalog and `ShopOrder`s, with authenticated product management.",
+        &[
+            ("src/main.rs", include_str!("templates/shop/main.rs")),
+            ("src/product.rs", include_str!("templates/shop/product.rs")),
+            ("src/order.rs", include_str!("templates/shop/order.rs")),
+            ("tests__hurl/shop.hurl", include_str!("templates/shop/test.hurl")),
+        ],
+    ),
+];
+
+pub fn run(args: Args) -> CliResult {
+    let [name] = args.positional.as_slice() else {
+        Err("Usage: tailwag new <name> [--template minimal|auth|shop]")?
+    };
+    validate_project_name(name)?;
+
+    let template_name = args.option("template").unwrap_or("minimal");
+    let Some((_, _, template_files)) = TEMPLATES.iter().find(|(t, _, _)| *t == template_name)
+    else {
+        let available = TEMPLATES
+            .iter()
+            .map(|(t, description, _)| format!("    {t:<10}{description}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(format!("Unknown template `{template_name}`. Available templates:\n{available}"))?
+    };
+
+    let root = Path::new(name);
+    if root.exists() {
+        Err(format!("Destination `{}` already exists.", root.display()))?;
+    }
+
+    for (path, contents) in COMMON_FILES.iter().chain(template_files.iter()) {
+        let contents = render(contents, name, template_name);
+        let path = root.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, contents)?;
+    }
+
+    println!("Created {} from the `{template_name}` template.\n", service_name(name));
+    println!("Get started with:");
+    println!("    cd {name}");
+    println!("    docker compose up -d");
+    println!("    cargo run");
+    Ok(())
+}
+
+/// Fills in the `{{...}}` placeholders in a template file. Any other `{{...}}`
+/// tokens (e.g. hurl variables) are left as-is.
+fn render(
+    contents: &str,
+    project_name: &str,
+    template_name: &str,
+) -> String {
+    contents
+        .replace("{{project_name}}", project_name)
+        .replace("{{service_name}}", &service_name(project_name))
+        .replace("{{template}}", template_name)
+}
+
+fn validate_project_name(name: &str) -> CliResult {
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        Err(format!(
+            "Invalid project name `{name}`. Names must start with a letter, and only contain \
+             letters, numbers, `-` or `_`."
+        ))?;
+    }
+    Ok(())
+}
+
+/// `my_tailwag-app` -> `My Tailwag App Service`
+fn service_name(project_name: &str) -> String {
+    let words = project_name
+        .split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or(String::new(), |c| c.to_uppercase().chain(chars).collect())
+        })
+        .collect::<Vec<_>>();
+    format!("{} Service", words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Variables that hurl captures at runtime, which must survive rendering.
+    const HURL_VARIABLES: &[&str] = &["{{email_address}}", "{{todo_id}}", "{{access_token}}"];
+
+    #[test]
+    fn validates_project_names() {
+        for name in ["my_app", "my-app", "App2"] {
+            assert!(validate_project_name(name).is_ok(), "{name} should be valid");
+        }
+        for name in ["", "2app", "_app", "my app", "../app", "my/app"] {
+            assert!(validate_project_name(name).is_err(), "{name} should be invalid");
+        }
+    }
+
+    #[test]
+    fn rejects_extra_arguments() {
+        let args = Args {
+            positional: vec!["my_app".to_string(), "extra".to_string()],
+            ..Default::default()
+        };
+        let err = run(args).unwrap_err();
+        assert!(err.to_string().starts_with("Usage:"), "{err}");
+        assert!(!Path::new("my_app").exists());
+    }
+
+    #[test]
+    fn builds_service_names() {
+        assert_eq!(service_name("my_tailwag-app"), "My Tailwag App Service");
+        assert_eq!(service_name("shop"), "Shop Service");
+        assert_eq!(service_name("a__b"), "A B Service");
+    }
+
+    #[test]
+    fn renders_every_placeholder() {
+        for (template_name, _, template_files) in TEMPLATES {
+            for (path, contents) in COMMON_FILES.iter().chain(template_files.iter()) {
+                let mut rendered = render(contents, "my_app", template_name);
+                if path.ends_with(".hurl") {
+                    for variable in HURL_VARIABLES {
+                        rendered = rendered.replace(variable, "");
+                    }
+                }
+                assert!(
+                    !rendered.contains("{{") && !rendered.contains("}}"),
+                    "{template_name}/{path} has unrendered placeholders:\n{rendered}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn substitutes_names() {
+        let (_, _, files) = TEMPLATES.iter().find(|(t, _, _)| *t == "minimal").unwrap();
+        let (_, main) = files.iter().find(|(path, _)| *path == "src/main.rs").unwrap();
+        assert!(
+            render(main, "my_app", "minimal").contains("WebService::builder(\"My App Service\")")
+        );
+        let (_, cargo_toml) = COMMON_FILES[0];
+        assert!(render(cargo_toml, "my_app", "minimal").contains("name = \"my_app\""));
+    }
+}
@@ -0,0 +1,16 @@
+use tailwag::prelude::*;
+
+derive_magic! {
+    #[get_policy(RoutePolicy::RequireAuthentication)]
+    #[list_policy(RoutePolicy::RequireAuthentication)]
+    #[post_policy(RoutePolicy::RequireAuthentication)]
+    #[patch_policy(RoutePolicy::RequireAuthentication)]
+    #[delete_policy(RoutePolicy::RequireAuthentication)]
+    pub struct Note {
+        id: uuid::Uuid,
+        #[display]
+        title: String,
+        #[no_filter]
+        body: String,
+    }
+}
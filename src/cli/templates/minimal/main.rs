@@ -0,0 +1,14 @@
+use tailwag::prelude::*;
+
+mod todo;
+use todo::Todo;
+
+#[tokio::main]
+async fn main() {
+    WebService::builder("{{service_name}}")
+        .with_resource::<Todo>()
+        .build_service()
+        .run()
+        .await
+        .expect("Web service crashed.");
+}
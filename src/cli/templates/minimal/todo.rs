@@ -0,0 +1,13 @@
+use tailwag::prelude::*;
+
+derive_magic! {
+    pub struct Todo {
+        id: uuid::Uuid,
+        #[display]
+        title: String,
+        #[no_filter]
+        description: String,
+        due_date: chrono::NaiveDateTime,
+        completed: bool,
+    }
+}
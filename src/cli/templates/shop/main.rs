@@ -0,0 +1,27 @@
+use tailwag::{
+    prelude::*,
+    web::auth::gateway::{
+        self,
+        extract_session,
+    },
+};
+
+mod order;
+mod product;
+use order::ShopOrder;
+use product::Product;
+
+#[tokio::main]
+async fn main() {
+    WebService::builder("{{service_name}}")
+        .with_middleware(extract_session)
+        .post_public("/login", gateway::login)
+        .post_public("/register", gateway::register)
+        .get_public("/", || "Hello, world!".to_string())
+        .with_resource::<Product>()
+        .with_resource::<ShopOrder>()
+        .build_service()
+        .run()
+        .await
+        .expect("Web service crashed.");
+}
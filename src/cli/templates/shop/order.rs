@@ -0,0 +1,13 @@
+use tailwag::prelude::*;
+
+derive_magic! {
+    #[post_policy(RoutePolicy::Public)]
+    pub struct ShopOrder {
+        id: uuid::Uuid,
+        #[display]
+        customer_name: String,
+        customer_email: String,
+        status: String,
+        total_usd_cents: i64,
+    }
+}
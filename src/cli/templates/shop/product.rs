@@ -0,0 +1,18 @@
+use tailwag::prelude::*;
+
+derive_magic! {
+    // Anyone can browse the catalog, but only logged in users can manage it.
+    #[get_policy(RoutePolicy::Public)]
+    #[list_policy(RoutePolicy::Public)]
+    #[post_policy(RoutePolicy::RequireAuthentication)]
+    #[patch_policy(RoutePolicy::RequireAuthentication)]
+    #[delete_policy(RoutePolicy::RequireAuthentication)]
+    pub struct Product {
+        id: uuid::Uuid,
+        #[display]
+        name: String,
+        #[no_filter]
+        description: String,
+        price_usd_cents: i64,
+    }
+}
@@ -56,6 +56,9 @@ pub mod macros {
 #[cfg(feature = "forms")]
 pub use tailwag_forms as forms;
 
+/// Schema snapshots and migration planning for the `tailwag migrate` command.
+pub mod migrate;
+
 pub mod prelude {
     pub use super::derive_magic;
     pub use super::orm::data_manager::traits::WithFilter;
@@ -1,18 +1,57 @@
+mod cli;
+
+use cli::Args;
+
 fn main() {
     let mut args = std::env::args();
     let _app_name = args.next().expect("No command name found in Args.");
-    match args.next().as_deref() {
-        Some("new") => println!("new not currently supported"),
-        Some("migrate") => println!("Migrate not currenlty supported."),
-        Some("deploy") => println!("deploy not currently supported"),
+    let result = match args.next().as_deref() {
+        Some("new") => Args::parse(args, &[], &["template"]).and_then(cli::new::run),
+        Some("generate") => Args::parse(args, &["dry-run"], &["policy", "read-policy", "main"])
+            .and_then(cli::generate::run),
+        Some("migrate") => Args::parse(
+            args,
+            &["dry-run", "allow-drop"],
+            &["schema", "name", "dir", "database-url"],
+        )
+        .and_then(cli::migrate::run),
+        Some("deploy") => {
+            println!("deploy not currently supported");
+            Ok(())
+        },
         // Some("connect") => todo!(),
-        // Some("run") => todo!(), // Thinkign: `tw run gui` or `tw run webservice` or `tw run tasks` / `tw run all`
-        // Some(_) | None => print_help(),
-        Some(_) | None => println!("Nothing is currently supported!"),
+        // Some("run") => todo!(), // Thinkign: `tw run gui` or `tw run webservice` or `tw run
+        // tasks` / `tw run all`
+        Some(action) => {
+            print_help();
+            Err(format!("Unknown action `{action}`").into())
+        },
+        None => {
+            print_help();
+            Ok(())
+        },
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
     }
 }
 
-#[allow(unused)]
 fn print_help() {
-    println!("Usage: tailwag <action> <filepath>");
+    println!("Usage: tailwag <action> [options]");
+    println!();
+    println!("Actions:");
+    println!("    new <name> [--template minimal|auth|shop]");
+    println!("        Create a new tailwag project.");
+    println!(
+        "    generate resource <Name> [field:type ...] [--policy public|authenticated] \
+         [--read-policy public|authenticated] [--main <path>] [--dry-run]"
+    );
+    println!("        Generate a new resource and register it with the service.");
+    println!(
+        "    migrate [up] --schema <file> [--dry-run] [--allow-drop] [--name <name>] [--dir \
+         <dir>] [--database-url <url>]"
+    );
+    println!("    migrate down [--dry-run] [--dir <dir>] [--database-url <url>]");
+    println!("        Diff a schema file against the database and apply or revert migrations.");
 }
@@ -0,0 +1,743 @@
+//! Schema snapshots and migration planning, used by the `tailwag migrate`
+//! command.
+//!
+//! The CLI reads the tables a service expects from a [`SchemaSnapshot`] JSON
+//! file, diffs it against the live database, and plans the DDL needed to bring
+//! the database up to date.
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+/// The set of tables a service expects to exist, keyed by table name.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaSnapshot {
+    pub tables: BTreeMap<String, TableSchema>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableSchema {
+    pub name:    String,
+    pub columns: Vec<ColumnSchema>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnSchema {
+    pub name:        String,
+    pub sql_type:    String,
+    pub nullable:    bool,
+    pub primary_key: bool,
+    pub references:  Option<ColumnReference>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnReference {
+    pub table:  String,
+    pub column: String,
+}
+
+/// A single reversible schema change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MigrationStep {
+    pub up:   String,
+    pub down: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PlanOptions {
+    /// Drop columns that the service no longer defines. Off by default - a
+    /// renamed field looks like a drop and an add, and the down migration
+    /// can't bring the dropped data back.
+    pub allow_drop: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MigrationPlan {
+    pub steps:    Vec<MigrationStep>,
+    /// Changes that were detected but deliberately not planned, or that may
+    /// fail / lose data.
+    pub warnings: Vec<String>,
+}
+
+impl SchemaSnapshot {
+    pub fn with_table(
+        mut self,
+        table: TableSchema,
+    ) -> Self {
+        self.tables.insert(table.name.clone(), table);
+        self
+    }
+}
+
+impl TableSchema {
+    fn column(
+        &self,
+        name: &str,
+    ) -> Option<&ColumnSchema> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// The primary key columns, in column order.
+    fn primary_key(&self) -> Vec<&str> {
+        self.columns.iter().filter(|c| c.primary_key).map(|c| c.name.as_str()).collect()
+    }
+
+    fn create_sql(&self) -> String {
+        let columns = self
+            .columns
+            .iter()
+            .map(|column| format!("    {}", column.definition_sql(true)))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        format!("CREATE TABLE {} (\n{}\n);", quote_ident(&self.name), columns)
+    }
+}
+
+impl ColumnSchema {
+    /// The column definition, as used in `CREATE TABLE` and `ADD COLUMN`.
+    /// Primary keys on existing tables are managed as table constraints
+    /// instead, so `inline_primary_key` is only set when creating a table.
+    fn definition_sql(
+        &self,
+        inline_primary_key: bool,
+    ) -> String {
+        let mut sql = format!("{} {}", quote_ident(&self.name), self.sql_type.to_uppercase());
+        if self.primary_key && inline_primary_key {
+            sql.push_str(" PRIMARY KEY");
+        } else if !self.nullable {
+            sql.push_str(" NOT NULL");
+        }
+        if let Some(reference) = &self.references {
+            sql.push_str(&format!(
+                " REFERENCES {}({})",
+                quote_ident(&reference.table),
+                quote_ident(&reference.column)
+            ));
+        }
+        sql
+    }
+}
+
+impl MigrationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The full `up` script, in the order the steps must be applied.
+    pub fn up_sql(&self) -> String {
+        self.steps.iter().map(|step| step.up.as_str()).collect::<Vec<_>>().join("\n\n")
+    }
+
+    /// The full `down` script, which undoes the steps in reverse order.
+    pub fn down_sql(&self) -> String {
+        self.steps
+            .iter()
+            .rev()
+            .map(|step| step.down.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Plans the DDL needed to take the database from `current` to `target`.
+///
+/// New tables are created in foreign-key dependency order, followed by
+/// column, foreign key and primary key changes on existing tables. Tables that
+/// only exist in `current` are never dropped, and columns are only dropped
+/// with [`PlanOptions::allow_drop`] - otherwise they are reported in the
+/// plan's warnings.
+///
+/// Constraints on existing tables are assumed to use Postgres' default names
+/// (`<table>_pkey` and `<table>_<column>_fkey`), which is what `CREATE TABLE`
+/// and `ADD COLUMN` produce.
+///
+/// Fails if the primary key of a table that other tables reference would
+/// change, since the referencing foreign keys depend on it.
+pub fn plan(
+    current: &SchemaSnapshot,
+    target: &SchemaSnapshot,
+    options: &PlanOptions,
+) -> Result<MigrationPlan, String> {
+    let mut plan = MigrationPlan::default();
+
+    let new_tables: Vec<&TableSchema> = target
+        .tables
+        .values()
+        .filter(|t| !current.tables.contains_key(&t.name))
+        .collect();
+    for table in sort_by_dependencies(new_tables) {
+        plan.steps.push(MigrationStep {
+            up:   table.create_sql(),
+            down: format!("DROP TABLE {};", quote_ident(&table.name)),
+        });
+    }
+
+    for (name, target_table) in &target.tables {
+        let Some(current_table) = current.tables.get(name) else {
+            continue;
+        };
+        let referenced_by = [current, target]
+            .iter()
+            .flat_map(|snapshot| snapshot.tables.values())
+            .filter(|t| {
+                t.columns
+                    .iter()
+                    .any(|c| c.references.as_ref().is_some_and(|r| &r.table == name))
+            })
+            .map(|t| t.name.as_str())
+            .collect::<BTreeSet<_>>();
+        plan_table_changes(current_table, target_table, &referenced_by, options, &mut plan)?;
+    }
+
+    for name in current.tables.keys().filter(|name| !target.tables.contains_key(*name)) {
+        plan.warnings
+            .push(format!("Table {name} is not in the target schema. It will not be dropped."));
+    }
+
+    Ok(plan)
+}
+
+fn plan_table_changes(
+    current: &TableSchema,
+    target: &TableSchema,
+    referenced_by: &BTreeSet<&str>,
+    options: &PlanOptions,
+    plan: &mut MigrationPlan,
+) -> Result<(), String> {
+    let table = quote_ident(&target.name);
+    for column in &target.columns {
+        let name = quote_ident(&column.name);
+        let Some(existing) = current.column(&column.name) else {
+            plan.steps.push(MigrationStep {
+                up:   add_column_sql(&target.name, column, &mut plan.warnings),
+                down: format!("ALTER TABLE {table} DROP COLUMN {name};"),
+            });
+            continue;
+        };
+
+        let (old_type, new_type) =
+            (canonical_type(&existing.sql_type), canonical_type(&column.sql_type));
+        if old_type != new_type {
+            plan.steps.push(MigrationStep {
+                up:   format!(
+                    "ALTER TABLE {table} ALTER COLUMN {name} TYPE {} USING {name}::{};",
+                    new_type.to_uppercase(),
+                    new_type
+                ),
+                down: format!(
+                    "ALTER TABLE {table} ALTER COLUMN {name} TYPE {} USING {name}::{};",
+                    old_type.to_uppercase(),
+                    old_type
+                ),
+            });
+        }
+        if existing.nullable != column.nullable && !column.primary_key {
+            let (up, down) = if column.nullable {
+                ("DROP NOT NULL", "SET NOT NULL")
+            } else {
+                ("SET NOT NULL", "DROP NOT NULL")
+            };
+            plan.steps.push(MigrationStep {
+                up:   format!("ALTER TABLE {table} ALTER COLUMN {name} {up};"),
+                down: format!("ALTER TABLE {table} ALTER COLUMN {name} {down};"),
+            });
+        }
+        if existing.references != column.references {
+            let constraint = quote_ident(&format!("{}_{}_fkey", target.name, column.name));
+            let drop = format!("ALTER TABLE {table} DROP CONSTRAINT {constraint};");
+            let add = |reference: &ColumnReference| {
+                format!(
+                    "ALTER TABLE {table} ADD CONSTRAINT {constraint} FOREIGN KEY ({name}) \
+                     REFERENCES {}({});",
+                    quote_ident(&reference.table),
+                    quote_ident(&reference.column)
+                )
+            };
+            let up = [
+                existing.references.as_ref().map(|_| drop.clone()),
+                column.references.as_ref().map(add),
+            ];
+            let down = [
+                column.references.as_ref().map(|_| drop.clone()),
+                existing.references.as_ref().map(add),
+            ];
+            plan.steps.push(MigrationStep {
+                up:   up.into_iter().flatten().collect::<Vec<_>>().join("\n"),
+                down: down.into_iter().flatten().collect::<Vec<_>>().join("\n"),
+            });
+        }
+    }
+
+    let dropped_columns: Vec<&ColumnSchema> =
+        current.columns.iter().filter(|c| target.column(&c.name).is_none()).collect();
+
+    // Columns that are kept around (because drops weren't allowed) keep their
+    // part of the primary key too.
+    let current_key = current.primary_key();
+    let mut target_key = target.primary_key();
+    if !options.allow_drop {
+        target_key
+            .extend(dropped_columns.iter().filter(|c| c.primary_key).map(|c| c.name.as_str()));
+    }
+    if current_key.iter().collect::<BTreeSet<_>>() != target_key.iter().collect::<BTreeSet<_>>() {
+        if !referenced_by.is_empty() {
+            Err(format!(
+                "Can't change the primary key of {} - it is referenced by {}. Drop those foreign \
+                 keys first, or migrate this change by hand.",
+                target.name,
+                referenced_by.iter().copied().collect::<Vec<_>>().join(", ")
+            ))?;
+        }
+        let constraint = quote_ident(&format!("{}_pkey", target.name));
+        let drop = format!("ALTER TABLE {table} DROP CONSTRAINT {constraint};");
+        let add = |key: &[&str]| {
+            let columns = key.iter().map(|c| quote_ident(c)).collect::<Vec<_>>().join(", ");
+            format!("ALTER TABLE {table} ADD CONSTRAINT {constraint} PRIMARY KEY ({columns});")
+        };
+        let up = [
+            (!current_key.is_empty()).then(|| drop.clone()),
+            (!target_key.is_empty()).then(|| add(&target_key)),
+        ];
+        let down = [
+            (!target_key.is_empty()).then(|| drop.clone()),
+            (!current_key.is_empty()).then(|| add(&current_key)),
+        ];
+        plan.steps.push(MigrationStep {
+            up:   up.into_iter().flatten().collect::<Vec<_>>().join("\n"),
+            down: down.into_iter().flatten().collect::<Vec<_>>().join("\n"),
+        });
+    }
+
+    for column in dropped_columns {
+        if !options.allow_drop {
+            plan.warnings.push(format!(
+                "Column {}.{} is not in the target schema. It will not be dropped unless \
+                 --allow-drop is passed.",
+                current.name, column.name
+            ));
+            continue;
+        }
+        plan.warnings.push(format!(
+            "Dropping column {}.{} - the down migration re-adds it as nullable, but can't restore \
+             its data.",
+            current.name, column.name
+        ));
+        // The old rows come back as NULL, so the column can't be NOT NULL again.
+        let restored = ColumnSchema {
+            nullable: true,
+            ..column.clone()
+        };
+        plan.steps.push(MigrationStep {
+            up:   format!("ALTER TABLE {table} DROP COLUMN {};", quote_ident(&column.name)),
+            down: format!("ALTER TABLE {table} ADD COLUMN {};", restored.definition_sql(false)),
+        });
+    }
+    Ok(())
+}
+
+/// `ADD COLUMN` for a column on an existing table. A `NOT NULL` column is added
+/// with the type's default value (matching the `Default` that resources
+/// derive) to fill in the existing rows, and the default is dropped again
+/// afterwards.
+fn add_column_sql(
+    table_name: &str,
+    column: &ColumnSchema,
+    warnings: &mut Vec<String>,
+) -> String {
+    let table = quote_ident(table_name);
+    let add = format!("ALTER TABLE {table} ADD COLUMN {}", column.definition_sql(false));
+    if column.nullable {
+        return format!("{add};");
+    }
+    // There's no default row to point a foreign key at.
+    let default = column.references.is_none().then(|| default_value(&column.sql_type)).flatten();
+    let Some(default) = default else {
+        warnings.push(format!(
+            "Adding NOT NULL column {table_name}.{} will fail if the table already has rows.",
+            column.name
+        ));
+        return format!("{add};");
+    };
+    format!(
+        "{add} DEFAULT {default};\nALTER TABLE {table} ALTER COLUMN {} DROP DEFAULT;",
+        quote_ident(&column.name)
+    )
+}
+
+/// The SQL literal for the Rust `Default` of the types the ORM maps to
+/// `sql_type`.
+fn default_value(sql_type: &str) -> Option<&'static str> {
+    match canonical_type(sql_type).as_str() {
+        "text" | "character varying" => Some("''"),
+        "smallint" | "integer" | "bigint" | "real" | "double precision" | "numeric" => Some("0"),
+        "boolean" => Some("false"),
+        "uuid" => Some("'00000000-0000-0000-0000-000000000000'"),
+        "timestamp without time zone" => Some("'1970-01-01 00:00:00'"),
+        "timestamp with time zone" => Some("'1970-01-01 00:00:00+00'"),
+        _ => None,
+    }
+}
+
+/// Orders tables so that every table comes after the tables it references.
+/// References to tables outside of `tables` are assumed to already exist.
+fn sort_by_dependencies(tables: Vec<&TableSchema>) -> Vec<&TableSchema> {
+    let names: BTreeSet<&str> = tables.iter().map(|t| t.name.as_str()).collect();
+    let mut created: BTreeSet<&str> = BTreeSet::new();
+    let mut remaining = tables;
+    let mut sorted = Vec::new();
+
+    while !remaining.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|table| {
+            table.columns.iter().filter_map(|c| c.references.as_ref()).all(|r| {
+                r.table == table.name
+                    || !names.contains(r.table.as_str())
+                    || created.contains(r.table.as_str())
+            })
+        });
+        if ready.is_empty() {
+            // Circular references - there's no valid order, so fall back to alphabetical.
+            sorted.extend(blocked);
+            break;
+        }
+        created.extend(ready.iter().map(|t| t.name.as_str()));
+        sorted.extend(ready);
+        remaining = blocked;
+    }
+    sorted
+}
+
+/// Normalizes the various spellings of a Postgres type (e.g. `INT`, `int4`,
+/// `integer`) so that types from the schema file and from `information_schema`
+/// can be compared.
+pub fn canonical_type(sql_type: &str) -> String {
+    let sql_type = sql_type.trim().to_lowercase();
+    match sql_type.as_str() {
+        "bool" | "boolean" => "boolean",
+        "int" | "int4" | "integer" | "serial" => "integer",
+        "int8" | "bigint" | "bigserial" => "bigint",
+        "int2" | "smallint" => "smallint",
+        "float" | "float8" | "double precision" => "double precision",
+        "float4" | "real" => "real",
+        "varchar" | "character varying" => "character varying",
+        "timestamp" | "timestamp without time zone" => "timestamp without time zone",
+        "timestamptz" | "timestamp with time zone" => "timestamp with time zone",
+        other => other,
+    }
+    .to_string()
+}
+
+pub fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(
+        name: &str,
+        sql_type: &str,
+    ) -> ColumnSchema {
+        ColumnSchema {
+            name: name.to_string(),
+            sql_type: sql_type.to_string(),
+            nullable: false,
+            ..Default::default()
+        }
+    }
+
+    fn id() -> ColumnSchema {
+        ColumnSchema {
+            primary_key: true,
+            ..column("id", "uuid")
+        }
+    }
+
+    fn references(
+        name: &str,
+        table: &str,
+    ) -> ColumnSchema {
+        ColumnSchema {
+            references: Some(ColumnReference {
+                table:  table.to_string(),
+                column: "id".to_string(),
+            }),
+            ..column(name, "uuid")
+        }
+    }
+
+    fn table(
+        name: &str,
+        columns: Vec<ColumnSchema>,
+    ) -> TableSchema {
+        TableSchema {
+            name: name.to_string(),
+            columns,
+        }
+    }
+
+    fn snapshot(tables: Vec<TableSchema>) -> SchemaSnapshot {
+        tables.into_iter().fold(SchemaSnapshot::default(), SchemaSnapshot::with_table)
+    }
+
+    fn todo(columns: Vec<ColumnSchema>) -> SchemaSnapshot {
+        snapshot(vec![table("todo", [vec![id()], columns].concat())])
+    }
+
+    fn plan_with(
+        current: &SchemaSnapshot,
+        target: &SchemaSnapshot,
+        allow_drop: bool,
+    ) -> MigrationPlan {
+        plan(
+            current,
+            target,
+            &PlanOptions {
+                allow_drop,
+            },
+        )
+        .unwrap()
+    }
+
+    fn up(plan: &MigrationPlan) -> Vec<&str> {
+        plan.steps.iter().map(|step| step.up.as_str()).collect()
+    }
+
+    fn down(plan: &MigrationPlan) -> Vec<&str> {
+        plan.steps.iter().map(|step| step.down.as_str()).collect()
+    }
+
+    #[test]
+    fn creates_tables_in_dependency_order() {
+        let target = snapshot(vec![
+            table("a_order_item", vec![id(), references("order_id", "b_order")]),
+            table("b_order", vec![id(), references("customer_id", "c_customer")]),
+            table("c_customer", vec![id(), references("parent_id", "c_customer")]),
+        ]);
+        let plan = plan_with(&SchemaSnapshot::default(), &target, false);
+
+        assert_eq!(
+            up(&plan)[0],
+            "CREATE TABLE \"c_customer\" (\n    \"id\" UUID PRIMARY KEY,\n    \"parent_id\" UUID \
+             NOT NULL REFERENCES \"c_customer\"(\"id\")\n);"
+        );
+        assert!(up(&plan)[1].starts_with("CREATE TABLE \"b_order\""));
+        assert!(up(&plan)[2].starts_with("CREATE TABLE \"a_order_item\""));
+        assert_eq!(
+            plan.down_sql(),
+            "DROP TABLE \"a_order_item\";\n\nDROP TABLE \"b_order\";\n\nDROP TABLE \"c_customer\";"
+        );
+    }
+
+    #[test]
+    fn sort_by_dependencies_falls_back_on_cycles() {
+        let a = table("a", vec![references("b_id", "b")]);
+        let b = table("b", vec![references("a_id", "a")]);
+        let c = table("c", vec![references("external_id", "external")]);
+        let sorted = sort_by_dependencies(vec![&a, &b, &c]);
+        let names: Vec<&str> = sorted.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn adds_columns() {
+        let current = todo(vec![]);
+        let target = todo(vec![
+            ColumnSchema {
+                nullable: true,
+                ..column("notes", "text")
+            },
+            column("title", "varchar"),
+            column("done", "bool"),
+            column("due_date", "timestamp"),
+        ]);
+        let plan = plan_with(&current, &target, false);
+
+        assert_eq!(
+            up(&plan),
+            [
+                "ALTER TABLE \"todo\" ADD COLUMN \"notes\" TEXT;",
+                "ALTER TABLE \"todo\" ADD COLUMN \"title\" VARCHAR NOT NULL DEFAULT '';\nALTER \
+                 TABLE \"todo\" ALTER COLUMN \"title\" DROP DEFAULT;",
+                "ALTER TABLE \"todo\" ADD COLUMN \"done\" BOOL NOT NULL DEFAULT false;\nALTER \
+                 TABLE \"todo\" ALTER COLUMN \"done\" DROP DEFAULT;",
+                "ALTER TABLE \"todo\" ADD COLUMN \"due_date\" TIMESTAMP NOT NULL DEFAULT \
+                 '1970-01-01 00:00:00';\nALTER TABLE \"todo\" ALTER COLUMN \"due_date\" DROP \
+                 DEFAULT;",
+            ]
+        );
+        assert_eq!(
+            down(&plan),
+            [
+                "ALTER TABLE \"todo\" DROP COLUMN \"notes\";",
+                "ALTER TABLE \"todo\" DROP COLUMN \"title\";",
+                "ALTER TABLE \"todo\" DROP COLUMN \"done\";",
+                "ALTER TABLE \"todo\" DROP COLUMN \"due_date\";",
+            ]
+        );
+        assert!(plan.warnings.is_empty());
+    }
+
+    #[test]
+    fn warns_about_not_null_columns_without_a_default() {
+        let current = todo(vec![]);
+        let target = todo(vec![references("owner_id", "account"), column("tags", "jsonb")]);
+        let plan = plan_with(&current, &target, false);
+
+        assert_eq!(
+            up(&plan),
+            [
+                "ALTER TABLE \"todo\" ADD COLUMN \"owner_id\" UUID NOT NULL REFERENCES \
+                 \"account\"(\"id\");",
+                "ALTER TABLE \"todo\" ADD COLUMN \"tags\" JSONB NOT NULL;",
+            ]
+        );
+        assert_eq!(plan.warnings.len(), 2);
+        assert!(plan.warnings[0].contains("todo.owner_id"));
+        assert!(plan.warnings[1].contains("todo.tags"));
+    }
+
+    #[test]
+    fn only_drops_columns_when_allowed() {
+        let current = todo(vec![column("title", "text")]);
+        let target = todo(vec![]);
+
+        let plan_without_drop = plan_with(&current, &target, false);
+        assert!(plan_without_drop.is_empty());
+        assert_eq!(plan_without_drop.warnings.len(), 1);
+        assert!(plan_without_drop.warnings[0].contains("--allow-drop"));
+
+        let plan_with_drop = plan_with(&current, &target, true);
+        assert_eq!(up(&plan_with_drop), ["ALTER TABLE \"todo\" DROP COLUMN \"title\";"]);
+        // Old rows come back as NULL, so the column is restored as nullable.
+        assert_eq!(down(&plan_with_drop), ["ALTER TABLE \"todo\" ADD COLUMN \"title\" TEXT;"]);
+        assert_eq!(plan_with_drop.warnings.len(), 1);
+        assert!(plan_with_drop.warnings[0].contains("nullable"));
+    }
+
+    #[test]
+    fn changes_column_types() {
+        let current = todo(vec![column("count", "int4")]);
+        assert!(plan_with(&current, &todo(vec![column("count", "INTEGER")]), false).is_empty());
+
+        let plan = plan_with(&current, &todo(vec![column("count", "int8")]), false);
+        assert_eq!(
+            up(&plan),
+            ["ALTER TABLE \"todo\" ALTER COLUMN \"count\" TYPE BIGINT USING \"count\"::bigint;"]
+        );
+        assert_eq!(
+            down(&plan),
+            ["ALTER TABLE \"todo\" ALTER COLUMN \"count\" TYPE INTEGER USING \"count\"::integer;"]
+        );
+    }
+
+    #[test]
+    fn changes_nullability() {
+        let current = todo(vec![column("title", "text")]);
+        let target = todo(vec![ColumnSchema {
+            nullable: true,
+            ..column("title", "text")
+        }]);
+        let plan = plan_with(&current, &target, false);
+        assert_eq!(up(&plan), ["ALTER TABLE \"todo\" ALTER COLUMN \"title\" DROP NOT NULL;"]);
+        assert_eq!(down(&plan), ["ALTER TABLE \"todo\" ALTER COLUMN \"title\" SET NOT NULL;"]);
+    }
+
+    #[test]
+    fn changes_foreign_keys() {
+        let current = todo(vec![column("owner_id", "uuid")]);
+        let target = todo(vec![references("owner_id", "account")]);
+        let added = plan_with(&current, &target, false);
+        assert_eq!(
+            up(&added),
+            ["ALTER TABLE \"todo\" ADD CONSTRAINT \"todo_owner_id_fkey\" FOREIGN KEY \
+              (\"owner_id\") REFERENCES \"account\"(\"id\");"]
+        );
+        assert_eq!(down(&added), ["ALTER TABLE \"todo\" DROP CONSTRAINT \"todo_owner_id_fkey\";"]);
+
+        let changed = plan_with(&target, &todo(vec![references("owner_id", "user")]), false);
+        assert_eq!(
+            up(&changed),
+            ["ALTER TABLE \"todo\" DROP CONSTRAINT \"todo_owner_id_fkey\";\nALTER TABLE \"todo\" \
+              ADD CONSTRAINT \"todo_owner_id_fkey\" FOREIGN KEY (\"owner_id\") REFERENCES \
+              \"user\"(\"id\");"]
+        );
+        assert!(down(&changed)[0].ends_with("REFERENCES \"account\"(\"id\");"));
+    }
+
+    #[test]
+    fn changes_primary_keys() {
+        let current = todo(vec![column("slug", "text")]);
+        let target = todo(vec![ColumnSchema {
+            primary_key: true,
+            ..column("slug", "text")
+        }]);
+        let plan = plan_with(&current, &target, false);
+        assert_eq!(
+            up(&plan),
+            ["ALTER TABLE \"todo\" DROP CONSTRAINT \"todo_pkey\";\nALTER TABLE \"todo\" ADD \
+              CONSTRAINT \"todo_pkey\" PRIMARY KEY (\"id\", \"slug\");"]
+        );
+        assert_eq!(
+            down(&plan),
+            ["ALTER TABLE \"todo\" DROP CONSTRAINT \"todo_pkey\";\nALTER TABLE \"todo\" ADD \
+              CONSTRAINT \"todo_pkey\" PRIMARY KEY (\"id\");"]
+        );
+    }
+
+    #[test]
+    fn refuses_to_change_referenced_primary_keys() {
+        let current = snapshot(vec![
+            table("todo", vec![id(), column("slug", "text")]),
+            table("note", vec![id(), references("todo_id", "todo")]),
+        ]);
+        let target = snapshot(vec![
+            table(
+                "todo",
+                vec![
+                    id(),
+                    ColumnSchema {
+                        primary_key: true,
+                        ..column("slug", "text")
+                    },
+                ],
+            ),
+            table("note", vec![id(), references("todo_id", "todo")]),
+        ]);
+        let err = plan(&current, &target, &PlanOptions::default()).unwrap_err();
+        assert!(err.contains("primary key of todo"), "{err}");
+        assert!(err.contains("referenced by note"), "{err}");
+    }
+
+    #[test]
+    fn down_reverses_up() {
+        let current = todo(vec![column("title", "text")]);
+        let target = snapshot(vec![
+            table("todo", vec![id(), column("title", "varchar"), column("done", "bool")]),
+            table("tag", vec![id(), references("todo_id", "todo")]),
+        ]);
+        let plan = plan_with(&current, &target, false);
+
+        assert_eq!(plan.steps.len(), 3);
+        assert_eq!(plan.up_sql(), up(&plan).join("\n\n"));
+        let mut reversed = down(&plan);
+        reversed.reverse();
+        assert_eq!(plan.down_sql(), reversed.join("\n\n"));
+        assert!(plan.down_sql().ends_with("DROP TABLE \"tag\";"));
+    }
+
+    #[test]
+    fn canonicalizes_type_aliases() {
+        assert_eq!(canonical_type(" INT4 "), "integer");
+        assert_eq!(canonical_type("bool"), "boolean");
+        assert_eq!(canonical_type("float8"), "double precision");
+        assert_eq!(canonical_type("timestamptz"), "timestamp with time zone");
+        assert_eq!(canonical_type("varchar"), canonical_type("character varying"));
+        assert_eq!(canonical_type("UUID"), "uuid");
+    }
+}